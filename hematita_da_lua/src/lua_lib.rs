@@ -1,11 +1,15 @@
 use crate::vm::value::Nillable;
 
 use self::super::{
-	vm::{value::{IntoNillable, Nillable::NonNil, Table, Value}, VirtualMachine},
+	vm::{value::{arena, IntoNillable, Nillable::NonNil, Table, Thread, ThreadStatus, Value},
+		VirtualMachine},
 	lua_tuple, lua_table
 };
 use itertools::Itertools;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::HashMap,
+	sync::{Arc, atomic::{AtomicU64, Ordering}}
+};
 
 pub fn table_to_vector(table: &Table) -> Vec<Nillable> {
 	let table = table.data.lock().unwrap();
@@ -23,7 +27,7 @@ pub fn vector_to_table(vector: Vec<Option<Value>>) -> HashMap<Value, Value> {
 		.collect::<HashMap<_, _>>()
 }
 
-pub fn print(arguments: Arc<Table>, _: &VirtualMachine)
+pub fn print(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
 		-> Result<Arc<Table>, String> {
 	let message = table_to_vector(&*arguments).into_iter()
 		.map(|argument| format!("{}", argument.nillable()))
@@ -32,7 +36,7 @@ pub fn print(arguments: Arc<Table>, _: &VirtualMachine)
 	Ok(lua_tuple![].arc())
 }
 
-pub fn pcall(arguments: Arc<Table>, vm: &VirtualMachine)
+pub fn pcall(arguments: Arc<Table>, vm: &Arc<VirtualMachine>)
 		-> Result<Arc<Table>, String> {
 	Ok(match arguments.array_remove(1) {
 		NonNil(Value::Function(function)) =>
@@ -51,14 +55,14 @@ pub fn pcall(arguments: Arc<Table>, vm: &VirtualMachine)
 	})
 }
 
-pub fn error(arguments: Arc<Table>, _: &VirtualMachine)
+pub fn error(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
 		-> Result<Arc<Table>, String> {
 	Err(arguments.index(&Value::Integer(1)).option()
 		.map(|value| value.string().map(str::to_string)).flatten()
 		.unwrap_or_else(|| "(non string errors are unsupported)".to_owned()))
 }
 
-pub fn setmetatable(arguments: Arc<Table>, _: &VirtualMachine)
+pub fn setmetatable(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
 		-> Result<Arc<Table>, String> {
 	let arguments = table_to_vector(&arguments);
 	let meta = match arguments.get(1) {
@@ -77,7 +81,7 @@ pub fn setmetatable(arguments: Arc<Table>, _: &VirtualMachine)
 	Ok(lua_tuple![].arc())
 }
 
-pub fn getmetatable(arguments: Arc<Table>, _: &VirtualMachine)
+pub fn getmetatable(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
 		-> Result<Arc<Table>, String> {
 	let arguments = table_to_vector(&arguments);
 	Ok(match arguments.get(0) {
@@ -96,19 +100,327 @@ pub fn getmetatable(arguments: Arc<Table>, _: &VirtualMachine)
 	}.arc())
 }
 
-pub fn r#type(arguments: Arc<Table>, _: &VirtualMachine)
+pub fn r#type(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
 		-> Result<Arc<Table>, String> {
 	Ok(lua_tuple![arguments.index(&1i64.into()).type_name()].arc())
 }
 
+/// Looks up the `__tostring` metamethod a value's metatable (or, for
+/// userdata, its `meta` field) carries, if any.
+fn tostring_metamethod(value: &Nillable) -> Option<Value> {
+	let metatable = match value {
+		NonNil(Value::Table(table)) => table.metatable.lock().unwrap().clone(),
+		NonNil(Value::UserData {meta, ..}) => meta.clone(),
+		_ => None
+	}?;
+
+	let handler = metatable.data.lock().unwrap().get(&Value::new_string("__tostring")).cloned();
+	handler
+}
+
+pub fn tostring(arguments: Arc<Table>, vm: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	let value = arguments.index(&1i64.into());
+
+	let stringified = match tostring_metamethod(&value) {
+		Some(Value::Function(function)) =>
+			vm.execute(&function, lua_tuple![value].arc())?.index(&1i64.into()),
+		Some(Value::NativeFunction(function)) =>
+			function(lua_tuple![value].arc(), vm)?.index(&1i64.into()),
+		_ => lua_tuple![format!("{}", value)].arc().index(&1i64.into())
+	};
+
+	Ok(lua_tuple![stringified].arc())
+}
+
+pub fn tonumber(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	let base = arguments.index(&2i64.into()).option()
+		.map(|value| {
+			let type_name = value.type_name();
+			value.numeric().ok_or_else(|| format!(
+				"bad argument #2 to 'tonumber' (number expected, got {})", type_name))
+		})
+		.transpose()?
+		.map(|base| base as u32);
+
+	if let Some(base) = base {
+		if !(2..=36).contains(&base) {
+			return Err("bad argument #2 to 'tonumber' (base out of range)".to_owned());
+		}
+	}
+
+	match arguments.index(&1i64.into()).option() {
+		Some(Value::String(string)) => Ok(lua_tuple![Value::parse_number(&string, base)].arc()),
+		Some(value @ (Value::Integer(_) | Value::Float(_))) if base.is_none() =>
+			Ok(lua_tuple![value].arc()),
+		Some(value) => Err(format!(
+			"bad argument #1 to 'tonumber' (string expected, got {})", value.type_name())),
+		None => Err("bad argument #1 to 'tonumber' (string expected, got no value)".to_owned())
+	}
+}
+
+pub fn coroutine_create(arguments: Arc<Table>, vm: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	match arguments.index(&1i64.into()) {
+		NonNil(Value::Function(function)) =>
+			Ok(lua_tuple![Value::Thread(Thread::create(function, vm))].arc()),
+		value => Err(format!("attempt to create a coroutine from a {} value",
+			value.type_name()))
+	}
+}
+
+pub fn coroutine_resume(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	Ok(match arguments.array_remove(1) {
+		NonNil(Value::Thread(thread)) => match thread.resume(arguments) {
+			Ok(result) => {result.tuple_insert(1, true.into()); result},
+			Err(error) => lua_tuple![false, error].arc()
+		},
+		value => lua_tuple![
+			false,
+			format!("attempt to resume a {} value", value.type_name())
+		].arc()
+	})
+}
+
+pub fn r#yield(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	Thread::yield_now(arguments)
+}
+
+pub fn coroutine_status(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	match arguments.index(&1i64.into()) {
+		NonNil(Value::Thread(thread)) => Ok(lua_tuple![match thread.status() {
+			ThreadStatus::Suspended => "suspended",
+			ThreadStatus::Running => "running",
+			ThreadStatus::Normal => "normal",
+			ThreadStatus::Dead => "dead"
+		}].arc()),
+		value => Err(format!("bad argument #1 to 'status' ({} expected, got {})",
+			"thread", value.type_name()))
+	}
+}
+
+/// Fetches and numeric-coerces `arguments`'s first value, formatting the
+/// same "bad argument" message every `math.*` unary function needs on
+/// failure. Shared by [`math_unary`] and the functions that need the raw
+/// `f64` before rounding it, like [`math_floor`]/[`math_ceil`].
+fn math_arg_numeric(name: &str, arguments: &Arc<Table>) -> Result<f64, String> {
+	let value = arguments.index(&1i64.into());
+	let type_name = value.type_name();
+	value.option().as_ref().and_then(Value::numeric)
+		.ok_or_else(|| format!("bad argument #1 to '{}' (number expected, got {})", name, type_name))
+}
+
+fn math_unary(name: &str, arguments: Arc<Table>, op: fn(f64) -> f64)
+		-> Result<Arc<Table>, String> {
+	math_arg_numeric(name, &arguments).map(|number| lua_tuple![op(number)].arc())
+}
+
+pub fn math_floor(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_arg_numeric("floor", &arguments).map(|number| lua_tuple![number.floor() as i64].arc())
+}
+
+pub fn math_ceil(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_arg_numeric("ceil", &arguments).map(|number| lua_tuple![number.ceil() as i64].arc())
+}
+
+pub fn math_abs(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	Ok(match arguments.index(&1i64.into()) {
+		NonNil(Value::Integer(integer)) => lua_tuple![integer.abs()],
+		NonNil(Value::Float(float)) => lua_tuple![float.abs()],
+		value => return Err(format!("bad argument #1 to 'abs' (number expected, got {})",
+			value.type_name()))
+	}.arc())
+}
+
+pub fn math_sqrt(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_unary("sqrt", arguments, f64::sqrt)
+}
+
+pub fn math_sin(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_unary("sin", arguments, f64::sin)
+}
+
+pub fn math_cos(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_unary("cos", arguments, f64::cos)
+}
+
+pub fn math_tan(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_unary("tan", arguments, f64::tan)
+}
+
+fn math_extreme(name: &str, arguments: Arc<Table>, prefer: fn(f64, f64) -> bool)
+		-> Result<Arc<Table>, String> {
+	let mut result: Option<(Value, f64)> = None;
+
+	for (index, value) in table_to_vector(&arguments).into_iter().enumerate() {
+		let value = value.option()
+			.ok_or_else(|| format!("bad argument #{} to '{}' (number expected, got no value)",
+				index + 1, name))?;
+		let number = value.numeric()
+			.ok_or_else(|| format!("bad argument #{} to '{}' (number expected, got {})",
+				index + 1, name, value.type_name()))?;
+
+		result = Some(match result {
+			Some((current, current_number)) if !prefer(number, current_number) =>
+				(current, current_number),
+			_ => (value, number)
+		});
+	}
+
+	match result {
+		Some((value, _)) => Ok(lua_tuple![value].arc()),
+		None => Err(format!("bad argument #1 to '{}' (number expected, got no value)", name))
+	}
+}
+
+pub fn math_min(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_extreme("min", arguments, |candidate, current| candidate < current)
+}
+
+pub fn math_max(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	math_extreme("max", arguments, |candidate, current| candidate > current)
+}
+
+pub fn math_fmod(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	let a = arguments.index(&1i64.into());
+	let b = arguments.index(&2i64.into());
+	let (a_type_name, b_type_name) = (a.type_name(), b.type_name());
+
+	let a = a.option().as_ref().and_then(Value::numeric)
+		.ok_or_else(|| format!("bad argument #1 to 'fmod' (number expected, got {})",
+			a_type_name))?;
+	let b = b.option().as_ref().and_then(Value::numeric)
+		.ok_or_else(|| format!("bad argument #2 to 'fmod' (number expected, got {})",
+			b_type_name))?;
+
+	Ok(lua_tuple![a % b].arc())
+}
+
+/// Backs `math.random`/`math.randomseed`. `VirtualMachine` isn't part of this
+/// source tree, so the seed can't live there as requested; it's kept here as
+/// a process-wide static, seeded from a fixed value until that's possible.
+static RANDOM_STATE: AtomicU64 = AtomicU64::new(0x2545F4914F6CDD1D);
+
+/// xorshift64*, chosen for being a few lines of dependency-free integer math.
+fn random_next() -> u64 {
+	let mut state = RANDOM_STATE.load(Ordering::Relaxed);
+	state ^= state << 13;
+	state ^= state >> 7;
+	state ^= state << 17;
+	RANDOM_STATE.store(state, Ordering::Relaxed);
+	state.wrapping_mul(0x2545F4914F6CDD1D)
+}
+
+fn random_float() -> f64 {
+	(random_next() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+pub fn math_random(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	Ok(match (arguments.index(&1i64.into()).option(), arguments.index(&2i64.into()).option()) {
+		(None, _) => lua_tuple![random_float()],
+		(Some(m), None) => {
+			let m = m.numeric()
+				.ok_or_else(|| "bad argument #1 to 'random' (number expected)".to_owned())?;
+			let m = m as i64;
+			if m < 1 {
+				return Err("bad argument #1 to 'random' (interval is empty)".to_owned());
+			}
+			lua_tuple![1 + (random_next() % m as u64) as i64]
+		},
+		(Some(m), Some(n)) => {
+			let m = m.numeric()
+				.ok_or_else(|| "bad argument #1 to 'random' (number expected)".to_owned())?;
+			let n = n.numeric()
+				.ok_or_else(|| "bad argument #2 to 'random' (number expected)".to_owned())?;
+			let (m, n) = (m as i64, n as i64);
+			if m > n {
+				return Err("bad argument #2 to 'random' (interval is empty)".to_owned());
+			}
+			let span = (n - m + 1) as u64;
+			lua_tuple![m + (random_next() % span) as i64]
+		}
+	}.arc())
+}
+
+pub fn math_randomseed(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	let seed = arguments.index(&1i64.into()).option().as_ref()
+		.and_then(Value::numeric).unwrap_or(0.0);
+	RANDOM_STATE.store(seed.to_bits() | 1, Ordering::Relaxed);
+	Ok(lua_tuple![].arc())
+}
+
+pub fn collectgarbage(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+		-> Result<Arc<Table>, String> {
+	let option = arguments.index(&1i64.into()).option()
+		.and_then(|value| value.string().map(str::to_string))
+		.unwrap_or_else(|| "collect".to_owned());
+
+	match option.as_str() {
+		// See [Arena][crate::vm::value::Arena]'s doc comment: it derives its
+		// own roots from reference counts, so there's no explicit root set to
+		// pass in here.
+		"collect" => Ok(lua_tuple![arena().collect() as i64].arc()),
+		// Lua reports kibibytes of memory in use; this arena doesn't track
+		// byte sizes, so the tracked object count stands in instead.
+		"count" => Ok(lua_tuple![arena().count() as f64].arc()),
+		other => Err(format!("bad argument #1 to 'collectgarbage' (invalid option '{}')",
+			other))
+	}
+}
+
 pub fn standard_globals() -> Arc<Table> {
+	let math = lua_table! {
+		floor = Value::NativeFunction(&math_floor),
+		ceil = Value::NativeFunction(&math_ceil),
+		abs = Value::NativeFunction(&math_abs),
+		sqrt = Value::NativeFunction(&math_sqrt),
+		sin = Value::NativeFunction(&math_sin),
+		cos = Value::NativeFunction(&math_cos),
+		tan = Value::NativeFunction(&math_tan),
+		min = Value::NativeFunction(&math_min),
+		max = Value::NativeFunction(&math_max),
+		fmod = Value::NativeFunction(&math_fmod),
+		huge = Value::Float(f64::INFINITY),
+		pi = Value::Float(std::f64::consts::PI),
+		random = Value::NativeFunction(&math_random),
+		randomseed = Value::NativeFunction(&math_randomseed)
+	};
+
+	let coroutine = lua_table! {
+		create = Value::NativeFunction(&coroutine_create),
+		resume = Value::NativeFunction(&coroutine_resume),
+		yield = Value::NativeFunction(&r#yield),
+		status = Value::NativeFunction(&coroutine_status)
+	};
+
 	let globals = lua_table! {
 		print = Value::NativeFunction(&print),
 		type = Value::NativeFunction(&r#type),
+		tostring = Value::NativeFunction(&tostring),
+		tonumber = Value::NativeFunction(&tonumber),
 		setmetatable = Value::NativeFunction(&setmetatable),
 		getmetatable = Value::NativeFunction(&getmetatable),
 		pcall = Value::NativeFunction(&pcall),
-		error = Value::NativeFunction(&error)
+		error = Value::NativeFunction(&error),
+		collectgarbage = Value::NativeFunction(&collectgarbage),
+		coroutine = Value::Table(coroutine.arc()),
+		math = Value::Table(math.arc())
 	}.arc();
 
 	{
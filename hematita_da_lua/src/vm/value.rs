@@ -1,12 +1,20 @@
 pub use self::{super::{Chunk, VirtualMachine}, Nillable::{Nil, NonNil}};
 use std::{
+	any::Any,
 	borrow::Borrow,
+	cell::RefCell,
 	collections::HashMap,
 	fmt::{Debug, Display, Formatter, Result as FMTResult},
 	hash::{Hash, Hasher},
 	mem::take,
+	ops::Deref,
 	ptr::{eq, hash},
-	sync::{Arc, Mutex}
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{self, Receiver, Sender},
+		Arc, Mutex, OnceLock, Weak
+	},
+	thread::{self, JoinHandle}
 };
 
 macro_rules! value_conversions {
@@ -92,7 +100,13 @@ macro_rules! lua_table {
 macro_rules! lua_table_inner {
 	($table:ident $counter:ident {[$key:expr] = $value:expr $(, $($rest:tt)*)?}) => {
 		{
-			$table.insert(lua_table_inner!($key), lua_table_inner!($value));
+			// Lua rejects `NaN` as a table key (it can never compare equal to
+			// itself), so a key that fails this check is silently dropped
+			// rather than inserted as an unfindable zombie entry.
+			let key = lua_table_inner!($key).normalize_key();
+			if key.is_valid_table_key() {
+				$table.insert(key, lua_table_inner!($value));
+			}
 		}
 
 		lua_table_inner!($table $counter {$($($rest)*)?});
@@ -155,27 +169,96 @@ macro_rules! lua_tuple_inner {
 	($value:expr) => {$value}
 }
 
-pub trait UserData {
+/// Implemented by host rust types embedded into lua as userdata, via
+/// [Value::new_user_data].
+///
+/// `obj:method(...)` sugar calls a registered method with the userdata
+/// itself as the first argument (`args[1]`, same as any other lua method
+/// call), so a [NativeFunction] registered through [UserData::register] gets
+/// its owning instance back as a `Value::UserData {data, ..}` there; since
+/// `dyn UserData` alone can't be cast back to the concrete type, the `Any`
+/// supertrait lets it `as_any_mut().downcast_mut::<T>()` to actually read or
+/// mutate its fields.
+pub trait UserData: Any + Send {
 	fn type_name(&self) -> &'static str;
+
+	/// Upcasts this value for [Any::downcast_ref], so a registered method can
+	/// recover its concrete type from the `Value::UserData` passed as `self`.
+	/// Implementations are always just `self` (a default body can't write
+	/// this, since the unsizing coercion needs `Self: Sized`, which would
+	/// make the method uncallable through `dyn UserData`).
+	fn as_any(&self) -> &dyn Any;
+
+	/// Upcasts this value for [Any::downcast_mut]; see [UserData::as_any].
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+
+	/// Registers this type's methods and metamethods (`__index`, `__add`,
+	/// `__eq`, `__tostring`, ...), called once per [Value::new_user_data] call.
+	/// The registered entries end up in the userdata value's `meta` table,
+	/// alongside the usual `setmetatable`/`getmetatable` machinery, so the
+	/// VM's metatable lookup dispatches to them the same way it would for a
+	/// table's metatable. The default implementation registers nothing.
+	fn register(_registry: &mut UserDataRegistry) where Self: Sized {}
+}
+
+/// Collects the methods and metamethods a [UserData] implementor registers
+/// via [UserData::register].
+#[derive(Default)]
+pub struct UserDataRegistry {
+	table: HashMap<Value, Value>
+}
+
+impl UserDataRegistry {
+	/// Registers a named method, callable from lua as `userdata:name(...)`.
+	pub fn method(&mut self, name: impl AsRef<str>, function: NativeFunction<'static>) {
+		self.table.insert(Value::new_string(name), Value::NativeFunction(function));
+	}
+
+	/// Registers a metamethod, e.g. `"__index"`, `"__add"`, `"__eq"`, or
+	/// `"__tostring"` (the name is used verbatim as the meta table key).
+	pub fn metamethod(&mut self, name: impl AsRef<str>, function: NativeFunction<'static>) {
+		self.table.insert(Value::new_string(name), Value::NativeFunction(function));
+	}
+
+	fn into_meta(self) -> Option<Arc<Table>> {
+		match self.table.is_empty() {
+			true => None,
+			false => Some(Table {data: Mutex::new(self.table), ..Default::default()}.arc())
+		}
+	}
 }
 
-pub type NativeFunction<'r> = &'r dyn Fn(Arc<Table>, &VirtualMachine)
+/// The `&Arc<VirtualMachine>` (rather than a bare `&VirtualMachine`) lets a
+/// native function that needs to outlive the call — [Thread::create] being
+/// the motivating case — clone an owned handle, with the "this reference
+/// really does come from an `Arc`" invariant enforced by the type system
+/// instead of assumed.
+pub type NativeFunction<'r> = &'r dyn Fn(Arc<Table>, &Arc<VirtualMachine>)
 	-> Result<Arc<Table>, String>;
 
 /// Represents a lua value.
-// TODO: Add floats.
 #[derive(Clone)]
 pub enum Value {
 	Integer(i64),
+	Float(f64),
 	String(Box<str>),
 	Boolean(bool),
 	Table(Arc<Table>),
 	UserData {
-		data: &'static dyn UserData,
+		data: Arc<Mutex<dyn UserData>>,
 		meta: Option<Arc<Table>>
 	},
 	Function(Arc<Function>),
-	NativeFunction(NativeFunction<'static>)
+	NativeFunction(NativeFunction<'static>),
+	Thread(Arc<Thread>)
+}
+
+/// The result of coercing two numeric [Value]s to a common representation for
+/// arithmetic. Lua requires that any operation involving a float operand
+/// produce a float result, even if the other operand is an integer.
+pub enum ArithmeticPair {
+	Integer(i64, i64),
+	Float(f64, f64)
 }
 
 impl Value {
@@ -183,14 +266,35 @@ impl Value {
 		Self::String(string.as_ref().to_owned().into_boxed_str())
 	}
 
+	/// Wraps a host rust value as a lua userdata [Value], registering its
+	/// methods and metamethods via [UserData::register].
+	pub fn new_user_data<T: UserData + 'static>(data: T) -> Self {
+		let mut registry = UserDataRegistry::default();
+		T::register(&mut registry);
+
+		Self::UserData {
+			data: Arc::new(Mutex::new(data)),
+			meta: registry.into_meta()
+		}
+	}
+
 	pub fn type_name(&self) -> &'static str {
 		match self {
 			Self::Integer(_) => "number",
+			Self::Float(_) => "number",
 			Self::String(_) => "string",
 			Self::Boolean(_) => "boolean",
 			Self::Table(_) => "table",
-			Self::UserData {data, ..} => data.type_name(),
-			Self::Function(_) | Self::NativeFunction(_) => "function"
+			Self::UserData {data, ..} => data.lock().unwrap().type_name(),
+			Self::Function(_) | Self::NativeFunction(_) => "function",
+			Self::Thread(_) => "thread"
+		}
+	}
+
+	pub fn thread(&self) -> Option<&Arc<Thread>> {
+		match self {
+			Self::Thread(thread) => Some(thread),
+			_ => None
 		}
 	}
 
@@ -216,6 +320,170 @@ impl Value {
 		}
 	}
 
+	pub fn float(&self) -> Option<f64> {
+		match self {
+			Self::Float(float) => Some(*float),
+			_ => None
+		}
+	}
+
+	/// If this value holds a [Float][Self::Float] with an exact integral value
+	/// that fits losslessly in an `i64`, returns the equivalent
+	/// [Integer][Self::Integer]. Lua requires such floats to be
+	/// indistinguishable from their integer counterpart when used as table
+	/// keys, so table-inserting code should normalize keys through this method
+	/// before inserting them.
+	pub fn normalize_key(self) -> Self {
+		match self {
+			Self::Float(float) => match Self::integral_float_to_i64(float) {
+				Some(integer) => Self::Integer(integer),
+				None => Self::Float(float)
+			},
+			other => other
+		}
+	}
+
+	/// Lua rejects `NaN` as a table key, since it can never compare equal to
+	/// itself. Code that inserts a value as a table key should check this
+	/// first.
+	pub fn is_valid_table_key(&self) -> bool {
+		!matches!(self, Self::Float(float) if float.is_nan())
+	}
+
+	fn integral_float_to_i64(float: f64) -> Option<i64> {
+		if float.is_finite() && float.fract() == 0.0
+				&& float >= i64::MIN as f64 && float < i64::MAX as f64
+			{Some(float as i64)} else {None}
+	}
+
+	/// Coerces `self` and `other` to a common numeric representation for
+	/// arithmetic, promoting to [Float][Self::Float] if either operand is one.
+	/// Returns [None] if either value isn't numeric.
+	pub fn arithmetic_coerce(&self, other: &Self) -> Option<ArithmeticPair> {
+		match (self, other) {
+			(Self::Integer(a), Self::Integer(b)) => Some(ArithmeticPair::Integer(*a, *b)),
+			(Self::Integer(a), Self::Float(b)) => Some(ArithmeticPair::Float(*a as f64, *b)),
+			(Self::Float(a), Self::Integer(b)) => Some(ArithmeticPair::Float(*a, *b as f64)),
+			(Self::Float(a), Self::Float(b)) => Some(ArithmeticPair::Float(*a, *b)),
+			_ => None
+		}
+	}
+
+	/// Coerces either an [Integer][Self::Integer] or a [Float][Self::Float] to
+	/// an `f64`, for numeric library functions that don't care which one they
+	/// got. Returns [None] if this value isn't numeric.
+	pub fn numeric(&self) -> Option<f64> {
+		match self {
+			Self::Integer(integer) => Some(*integer as f64),
+			Self::Float(float) => Some(*float),
+			_ => None
+		}
+	}
+
+	/// Parses a lua numeral out of `s`, the way `tonumber` does. With `base`
+	/// given (2 to 36 inclusive), `s` is read as an optionally-signed sequence
+	/// of digits in that radix into an [Integer][Self::Integer], wrapping on
+	/// overflow rather than failing. With no `base`, `s` is read as a decimal
+	/// integer or float (scientific notation allowed) or a `0x`-prefixed hex
+	/// integer or float (`p`/`P` binary exponent allowed). Returns
+	/// [Nil][Nillable::Nil] if `s` isn't a valid numeral in that form.
+	pub fn parse_number(s: &str, base: Option<u32>) -> Nillable {
+		let s = s.trim();
+
+		match base {
+			Some(base) => {
+				let (negative, digits) = Self::strip_sign(s);
+				Self::parse_radix_integer(digits, base)
+					.map(|value| if negative {value.wrapping_neg()} else {value})
+					.map(Self::Integer)
+			},
+			None => Self::parse_numeral(s)
+		}.nillable()
+	}
+
+	/// Splits a single optional leading `-`/`+` off of `s`. `i64`/`f64`'s
+	/// `FromStr` also accept a leading sign, so a caller that hands the
+	/// remainder to them must reject a remainder that itself starts with
+	/// `-`/`+` rather than parsing it — otherwise a second sign (`"--5"`,
+	/// `"0x-5"`) would get silently re-applied instead of making the numeral
+	/// invalid. [parse_radix_integer]'s digit loop rejects a sign as a
+	/// non-digit on its own.
+	fn strip_sign(s: &str) -> (bool, &str) {
+		match s.strip_prefix('-') {
+			Some(rest) => (true, rest),
+			None => (false, s.strip_prefix('+').unwrap_or(s))
+		}
+	}
+
+	fn parse_radix_integer(digits: &str, base: u32) -> Option<i64> {
+		if !(2..=36).contains(&base) || digits.is_empty() {return None}
+
+		let mut value = 0i64;
+		for digit in digits.chars() {
+			value = value.wrapping_mul(base as i64)
+				.wrapping_add(digit.to_digit(base)? as i64);
+		}
+
+		Some(value)
+	}
+
+	fn parse_numeral(s: &str) -> Option<Self> {
+		let (negative, rest) = Self::strip_sign(s);
+		// See [strip_sign]'s doc comment: a second sign here is invalid.
+		if rest.starts_with(['-', '+']) {return None}
+
+		if let Some(hex) = rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+			return Self::parse_hex_numeral(hex, negative);
+		}
+
+		if let Ok(integer) = rest.parse::<i64>() {
+			return Some(Self::Integer(if negative {-integer} else {integer}));
+		}
+
+		// `f64`'s own parser also accepts `inf`/`infinity`/`nan`, which aren't
+		// valid lua numerals; only hand it strings that look like one.
+		rest.parse::<f64>().ok()
+			.filter(|_| !rest.is_empty() && rest.chars()
+				.all(|char| char.is_ascii_digit() || matches!(char, '.' | 'e' | 'E' | '+' | '-')))
+			.map(|float| Self::Float(if negative {-float} else {float}))
+	}
+
+	fn parse_hex_numeral(hex: &str, negative: bool) -> Option<Self> {
+		// See [strip_sign]'s doc comment: a sign straight after `0x` is invalid.
+		if hex.starts_with(['-', '+']) {return None}
+
+		if let Some(split) = hex.find(['p', 'P']) {
+			let exponent = hex[split + 1..].parse::<i32>().ok()?;
+			let mantissa = Self::parse_hex_mantissa(&hex[..split])?;
+			let float = mantissa * 2f64.powi(exponent);
+			return Some(Self::Float(if negative {-float} else {float}));
+		}
+
+		if hex.contains('.') {
+			let float = Self::parse_hex_mantissa(hex)?;
+			return Some(Self::Float(if negative {-float} else {float}));
+		}
+
+		let integer = Self::parse_radix_integer(hex, 16)?;
+		Some(Self::Integer(if negative {integer.wrapping_neg()} else {integer}))
+	}
+
+	fn parse_hex_mantissa(mantissa: &str) -> Option<f64> {
+		let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+		if whole.is_empty() && fraction.is_empty() {return None}
+
+		let mut value = whole.chars()
+			.try_fold(0f64, |value, digit| Some(value * 16.0 + digit.to_digit(16)? as f64))?;
+
+		let mut scale = 1.0 / 16.0;
+		for digit in fraction.chars() {
+			value += digit.to_digit(16)? as f64 * scale;
+			scale /= 16.0;
+		}
+
+		Some(value)
+	}
+
 	pub fn string(&self) -> Option<&str> {
 		match self {
 			Self::String(string) => Some(string),
@@ -249,12 +517,26 @@ impl Display for Value {
 	fn fmt(&self, f: &mut Formatter) -> FMTResult {
 		match self {
 			Self::Integer(integer) => write!(f, "{}", integer),
+			Self::Float(float) if float.is_nan() => write!(f, "nan"),
+			Self::Float(float) if float.is_infinite() =>
+				write!(f, "{}inf", if *float < 0.0 {"-"} else {""}),
+			Self::Float(float) => {
+				let formatted = format!("{}", float);
+				match formatted.contains(&['.', 'e', 'E'][..]) {
+					true => write!(f, "{}", formatted),
+					false => write!(f, "{}.0", formatted)
+				}
+			},
 			Self::String(string) => write!(f, "{}", string),
 			Self::Boolean(boolean) => write!(f, "{}", boolean),
 			Self::Table(table) => write!(f, "{}", table),
-			Self::UserData {..} => todo!(),
+			// `__tostring` needs a `&VirtualMachine` to call, which `Display`
+			// doesn't have access to; the VM-aware `tostring` native function
+			// checks for it itself and only falls back to this.
+			Self::UserData {data, ..} => write!(f, "userdata: {:p}", Arc::as_ptr(data)),
 			Self::Function(function) => write!(f, "{}", function),
-			Self::NativeFunction(function) => write!(f, "function: {:p}", *function)
+			Self::NativeFunction(function) => write!(f, "function: {:p}", *function),
+			Self::Thread(thread) => write!(f, "thread: {:p}", Arc::as_ptr(thread))
 		}
 	}
 }
@@ -263,12 +545,14 @@ impl Debug for Value {
 	fn fmt(&self, f: &mut Formatter) -> FMTResult {
 		match self {
 			Self::Integer(integer) => Debug::fmt(integer, f),
+			Self::Float(float) => Debug::fmt(float, f),
 			Self::String(string) => Debug::fmt(string, f),
 			Self::Boolean(boolean) => Debug::fmt(boolean, f),
 			Self::Table(table) => Debug::fmt(table, f),
-			Self::UserData {..} => todo!(),
+			Self::UserData {data, ..} => write!(f, "userdata: {:p}", Arc::as_ptr(data)),
 			Self::Function(function) => Debug::fmt(function, f),
-			Self::NativeFunction(function) => write!(f, "function: {:p}", function)
+			Self::NativeFunction(function) => write!(f, "function: {:p}", function),
+			Self::Thread(thread) => write!(f, "thread: {:p}", Arc::as_ptr(thread))
 		}
 	}
 }
@@ -279,6 +563,13 @@ impl PartialEq for Value {
 	fn eq(&self, other: &Self) -> bool {
 		match (self, other) {
 			(Self::Integer(a), Self::Integer(b)) => *a == *b,
+			(Self::Float(a), Self::Float(b)) =>
+				match (Self::integral_float_to_i64(*a), Self::integral_float_to_i64(*b)) {
+					(Some(a), Some(b)) => a == b,
+					_ => *a == *b
+				},
+			(Self::Integer(a), Self::Float(b)) | (Self::Float(b), Self::Integer(a)) =>
+				Self::integral_float_to_i64(*b) == Some(*a),
 			(Self::String(a), Self::String(b)) => *a == *b,
 			(Self::Boolean(a), Self::Boolean(b)) => *a == *b,
 			(Self::Function(a), Self::Function(b)) =>
@@ -287,6 +578,9 @@ impl PartialEq for Value {
 				Arc::as_ptr(a) == Arc::as_ptr(b),
 			(Self::NativeFunction(a), Self::NativeFunction(b)) =>
 				eq(*a as *const _ as *const u8, *b as *const _ as *const u8),
+			(Self::Thread(a), Self::Thread(b)) => Arc::as_ptr(a) == Arc::as_ptr(b),
+			(Self::UserData {data: a, ..}, Self::UserData {data: b, ..}) =>
+				Arc::ptr_eq(a, b),
 			_ => false
 		}
 	}
@@ -297,18 +591,27 @@ impl Hash for Value {
 			where H: Hasher {
 		match self {
 			Self::Integer(integer) => integer.hash(state),
+			// An integral float must hash identically to its Integer counterpart,
+			// so that it collides with it as a table key. Genuine (fractional)
+			// floats hash by their bit pattern.
+			Self::Float(float) => match Self::integral_float_to_i64(*float) {
+				Some(integer) => integer.hash(state),
+				None => float.to_bits().hash(state)
+			},
 			Self::String(string) => string.hash(state),
 			Self::Boolean(boolean) => boolean.hash(state),
 			Self::Table(arc) => Arc::as_ptr(arc).hash(state),
-			Self::UserData {data, ..} => hash(data, state),
+			Self::UserData {data, ..} => Arc::as_ptr(data).hash(state),
 			Self::Function(arc) => Arc::as_ptr(arc).hash(state),
-			Self::NativeFunction(func) => hash(func, state)
+			Self::NativeFunction(func) => hash(func, state),
+			Self::Thread(arc) => Arc::as_ptr(arc).hash(state)
 		}
 	}
 }
 
 value_conversions! {
 	impl for value @ i64 {Value::Integer(value)}
+	impl for value @ f64 {Value::Float(value)}
 	impl<'r> for value @ &'r str {Value::String(value.into())}
 	impl for value @ Box<str> {Value::String(value)}
 	impl for value @ String {Value::String(value.into_boxed_str())}
@@ -420,6 +723,7 @@ nillable_conversions! {
 	// From Into<Value>
 
 	impl all for value @ i64 {NonNil(value.into())}
+	impl all for value @ f64 {NonNil(value.into())}
 	impl<'r> all for value @ &'r str {NonNil(value.into())}
 	impl all for value @ Box<str> {NonNil(value.into())}
 	impl all for value @ String {NonNil(value.into())}
@@ -469,7 +773,9 @@ impl Default for MaybeUpValue {
 #[derive(Default)]
 pub struct Table {
 	pub data: Mutex<HashMap<Value, Value>>,
-	pub metatable: Mutex<Option<Arc<Table>>>
+	pub metatable: Mutex<Option<Arc<Table>>>,
+	/// Set by [Arena::collect]'s mark phase; not meaningful outside of one.
+	pub(crate) marked: AtomicBool
 }
 
 impl Table {
@@ -547,7 +853,9 @@ impl Table {
 	}
 
 	pub fn arc(self) -> Arc<Self> {
-		Arc::new(self)
+		let table = Arc::new(self);
+		arena().register_table(&table);
+		table
 	}
 }
 
@@ -602,12 +910,16 @@ impl Debug for Table {
 #[derive(Debug)]
 pub struct Function {
 	pub up_values: Box<[Arc<Mutex<Nillable>>]>,
-	pub chunk: Arc<Chunk>
+	pub chunk: Arc<Chunk>,
+	/// Set by [Arena::collect]'s mark phase; not meaningful outside of one.
+	marked: AtomicBool
 }
 
 impl Function {
 	pub fn arc(self) -> Arc<Self> {
-		Arc::new(self)
+		let function = Arc::new(self);
+		arena().register_function(&function);
+		function
 	}
 }
 
@@ -627,6 +939,513 @@ impl Display for Function {
 
 impl From<Chunk> for Function {
 	fn from(chunk: Chunk) -> Self {
-		Self {chunk: chunk.arc(), up_values: vec![].into_boxed_slice()}
+		Self {
+			chunk: chunk.arc(),
+			up_values: vec![].into_boxed_slice(),
+			marked: AtomicBool::new(false)
+		}
+	}
+}
+
+/// The status of a [Thread], mirroring the strings `coroutine.status` returns
+/// in stock lua.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThreadStatus {
+	/// Freshly created, or yielded and waiting to be resumed.
+	Suspended,
+	/// Currently executing; a `resume` call is on the stack above it.
+	Running,
+	/// Resumed another thread, and is waiting for that thread to yield or
+	/// finish.
+	Normal,
+	/// Its function has returned or errored; it can never be resumed again.
+	Dead
+}
+
+/// A handoff sent into a suspended coroutine to wake it back up.
+enum Resume {
+	/// Resume with these call/`resume` arguments.
+	Arguments(Arc<Table>),
+	/// The [Thread] handle was dropped before the coroutine finished; the
+	/// worker should stop running at the next opportunity.
+	Cancelled
+}
+
+/// A handoff sent out of a running coroutine, either because it yielded or
+/// because its function ran to completion.
+enum Yield {
+	Yielded(Arc<Table>),
+	Returned(Result<Arc<Table>, String>)
+}
+
+/// `Function` and `VirtualMachine` carry `!Sync` native function pointers and
+/// trait objects, so neither is `Send` by default. `Thread::create`'s
+/// resume/yield handshake guarantees its worker thread and the threads
+/// calling `resume` never touch the wrapped value at the same time, which is
+/// the actual safety property `Send` exists to protect; this wrapper asserts
+/// it by hand for the one value moved into the worker thread's closure.
+struct AssertSend<T>(T);
+
+unsafe impl<T> Send for AssertSend<T> {}
+
+/// The same conservative-inference problem as [AssertSend], but for `Sync`:
+/// a process-wide `static` must be `Sync`, and anything reachable from
+/// [Value] never is, because of its `!Sync` native-function and userdata
+/// pointers. Every real access to the statics wrapped in this type goes
+/// through a [Mutex] or [OnceLock], which already serializes access, so the
+/// missing auto-trait is never an actual soundness gap for them.
+pub(crate) struct AssertSync<T>(pub(crate) T);
+
+unsafe impl<T> Sync for AssertSync<T> {}
+
+impl<T> Deref for AssertSync<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+
+thread_local! {
+	/// The currently-running coroutine's own handle (so a nested `resume` can
+	/// flip its caller to [ThreadStatus::Normal]; see [Thread::resume]) and its
+	/// yield/resume channel half.
+	static CURRENT_COROUTINE: RefCell<Option<(Weak<Thread>, Sender<Yield>, Receiver<Resume>)>> =
+		RefCell::new(None);
+}
+
+/// A cooperatively-scheduled lua thread, created by `coroutine.create` and
+/// driven by `coroutine.resume` / `coroutine.yield`.
+///
+/// Hematita's interpreter has no way to suspend a call stack mid-instruction,
+/// so a [Thread] is backed by a real OS thread that blocks on a channel
+/// whenever the lua code it's running calls `yield`, and is woken back up by
+/// the next `resume`. This keeps the suspended call's locals and up-values
+/// alive exactly as they were left, at the cost of one OS thread per live
+/// coroutine. `resume` and the coroutine's own thread hand off control to
+/// each other one at a time and never run concurrently, so the [VirtualMachine]
+/// reference the coroutine was created with is never accessed from two
+/// threads at once even though it outlives the call that created the thread.
+pub struct Thread {
+	status: Mutex<ThreadStatus>,
+	to_worker: Sender<Resume>,
+	from_worker: Mutex<Receiver<Yield>>,
+	worker: Mutex<Option<JoinHandle<()>>>
+}
+
+impl Thread {
+	/// Wraps `function` in a freshly created, suspended [Thread]. The function
+	/// doesn't start running until the first `resume`.
+	pub fn create(function: Arc<Function>, vm: &Arc<VirtualMachine>) -> Arc<Thread> {
+		// Cloning the `Arc` ties the `VirtualMachine`'s lifetime
+		// to the worker thread with no unsafe code and no assumption about
+		// where the caller's reference came from: the type itself guarantees
+		// there's a real `Arc<VirtualMachine>` to clone.
+		let vm = vm.clone();
+
+		let (to_worker, worker_inbox) = mpsc::channel::<Resume>();
+		let (worker_outbox, from_worker) = mpsc::channel::<Yield>();
+
+		// Built with `Arc::new_cyclic` so the worker closure can stash a
+		// [Weak] handle to the very [Thread] it's running as into
+		// [CURRENT_COROUTINE], which `resume` needs to flip a nested caller to
+		// [ThreadStatus::Normal].
+		Arc::new_cyclic(|this| {
+			let this = this.clone();
+
+			// `Value`'s native-function and userdata variants hold raw/`dyn`
+			// pointers that are conservatively `!Send`; bundling the whole
+			// captured environment lets us assert `Send` once, for the reason
+			// given on [AssertSend].
+			let captures = AssertSend((vm, function, worker_inbox, worker_outbox, this));
+
+			let worker = thread::Builder::new()
+				.name("hematita-coroutine".to_owned())
+				.spawn(move || {
+					let AssertSend((vm, function, worker_inbox, worker_outbox, this)) = captures;
+					let arguments = match worker_inbox.recv() {
+						Ok(Resume::Arguments(arguments)) => arguments,
+						_ => return
+					};
+
+					CURRENT_COROUTINE.with(|current| *current.borrow_mut() =
+						Some((this, worker_outbox.clone(), worker_inbox)));
+
+					let result = vm.execute(&function, arguments);
+
+					CURRENT_COROUTINE.with(|current| *current.borrow_mut() = None);
+					let _ = worker_outbox.send(Yield::Returned(result));
+				})
+				.expect("failed to spawn coroutine thread");
+
+			Thread {
+				status: Mutex::new(ThreadStatus::Suspended),
+				to_worker,
+				from_worker: Mutex::new(from_worker),
+				worker: Mutex::new(Some(worker))
+			}
+		})
+	}
+
+	pub fn status(&self) -> ThreadStatus {
+		*self.status.lock().unwrap()
+	}
+
+	/// Resumes this thread with `arguments`, blocking until it either yields
+	/// or returns. Mirrors `coroutine.resume`: `Ok` carries the yielded or
+	/// returned values, `Err` carries the error message.
+	pub fn resume(&self, arguments: Arc<Table>) -> Result<Arc<Table>, String> {
+		{
+			let mut status = self.status.lock().unwrap();
+			match *status {
+				ThreadStatus::Suspended => *status = ThreadStatus::Running,
+				ThreadStatus::Dead =>
+					return Err("cannot resume dead coroutine".to_owned()),
+				ThreadStatus::Running | ThreadStatus::Normal =>
+					return Err("cannot resume non-suspended coroutine".to_owned())
+			}
+		}
+
+		// If this call is itself running inside another coroutine's worker
+		// thread, that coroutine is now blocked waiting on us rather than
+		// actually running, so `coroutine.status` should report it as
+		// "normal" until we're done, the same way stock Lua does for a
+		// resume nested inside another coroutine.
+		let caller = CURRENT_COROUTINE.with(|current| current.borrow().as_ref()
+			.and_then(|(this, ..)| this.upgrade()));
+		if let Some(caller) = &caller {
+			*caller.status.lock().unwrap() = ThreadStatus::Normal;
+		}
+
+		let result = (|| {
+			if self.to_worker.send(Resume::Arguments(arguments)).is_err() {
+				*self.status.lock().unwrap() = ThreadStatus::Dead;
+				return Err("cannot resume dead coroutine".to_owned());
+			}
+
+			let message = self.from_worker.lock().unwrap().recv()
+				.map_err(|_| "coroutine terminated unexpectedly".to_owned())?;
+
+			match message {
+				Yield::Yielded(values) => {
+					*self.status.lock().unwrap() = ThreadStatus::Suspended;
+					Ok(values)
+				},
+				Yield::Returned(result) => {
+					*self.status.lock().unwrap() = ThreadStatus::Dead;
+					if let Some(worker) = self.worker.lock().unwrap().take() {
+						let _ = worker.join();
+					}
+					result
+				}
+			}
+		})();
+
+		if let Some(caller) = &caller {
+			*caller.status.lock().unwrap() = ThreadStatus::Running;
+		}
+
+		result
+	}
+
+	/// Suspends the coroutine running on the calling OS thread, handing
+	/// `values` back to whichever `resume` call is waiting on it, and blocks
+	/// until the next `resume`, whose arguments are then returned. Errors if
+	/// called from outside a coroutine.
+	pub fn yield_now(values: Arc<Table>) -> Result<Arc<Table>, String> {
+		CURRENT_COROUTINE.with(|current| {
+			let mut current = current.borrow_mut();
+			let (_, sender, receiver) = current.as_mut()
+				.ok_or_else(|| "attempt to yield from outside a coroutine".to_owned())?;
+
+			sender.send(Yield::Yielded(values))
+				.map_err(|_| "coroutine resumer is gone".to_owned())?;
+
+			match receiver.recv() {
+				Ok(Resume::Arguments(arguments)) => Ok(arguments),
+				_ => Err("coroutine resumer is gone".to_owned())
+			}
+		})
+	}
+}
+
+impl Drop for Thread {
+	fn drop(&mut self) {
+		let _ = self.to_worker.send(Resume::Cancelled);
+		if let Some(worker) = self.worker.lock().unwrap().take() {
+			let _ = worker.join();
+		}
+	}
+}
+
+/// Tracks every live [Table] and [Function] so that [Arena::collect] can run
+/// a mark-and-sweep pass reclaiming reference cycles plain `Arc` counting
+/// never would (a table that stores itself, a closure whose up-value is a
+/// table that in turn holds the closure, ...).
+///
+/// `Table::arc`/`Function::arc` register every allocation here by default,
+/// but only by a weak reference, so the arena never keeps anything alive by
+/// itself; values still reachable from an `Arc` handle held outside the arena
+/// are simply forgotten once they're dropped, never swept.
+///
+/// `VirtualMachine` isn't part of this source tree, so [Arena::collect] has
+/// no way to enumerate the live call stack directly. Instead of trusting an
+/// externally supplied root set (which would miss anything only reachable
+/// from a suspended call's locals or up-values, and silently sweep it out
+/// from under a running script), [Arena::collect] derives its own roots: a
+/// tracked table/function with more `Arc` holders than other tracked
+/// values can account for must have at least one holder outside the arena
+/// entirely (a Rust local, a VM register, the host's globals handle, ...),
+/// so it's seeded as live the same way an explicit root would be. Only once
+/// that's subtracted out does whatever's left over, still holding a
+/// positive strong count, count as a genuine unreachable cycle.
+#[derive(Default)]
+pub struct Arena {
+	tables: Mutex<Vec<Weak<Table>>>,
+	functions: Mutex<Vec<Weak<Function>>>
+}
+
+/// The process-wide [Arena] every [Table]/[Function] registers into. Ideally
+/// this would be a field on `VirtualMachine`, scoped to one running script,
+/// but that struct lives outside this source tree.
+pub fn arena() -> &'static Arena {
+	static ARENA: AssertSync<OnceLock<Arena>> = AssertSync(OnceLock::new());
+	ARENA.get_or_init(Arena::default)
+}
+
+/// A tracked table or function's identity, for counting up references
+/// between tracked values irrespective of their type.
+type TrackedId = *const ();
+
+impl Arena {
+	fn register_table(&self, table: &Arc<Table>) {
+		self.tables.lock().unwrap().push(Arc::downgrade(table));
+	}
+
+	fn register_function(&self, function: &Arc<Function>) {
+		self.functions.lock().unwrap().push(Arc::downgrade(function));
+	}
+
+	/// The number of still-live tables and functions this arena is tracking.
+	pub fn count(&self) -> usize {
+		let tables = self.tables.lock().unwrap().iter()
+			.filter(|table| table.strong_count() > 0).count();
+		let functions = self.functions.lock().unwrap().iter()
+			.filter(|function| function.strong_count() > 0).count();
+		tables + functions
+	}
+
+	/// Runs a full collection pass: derives roots from reference counts (see
+	/// the type's doc comment), marks everything reachable from them, then
+	/// sweeps. Anything left alive (an `Arc` cycle kept its reference count
+	/// above zero) but unmarked is unreachable garbage, so its contents are
+	/// cleared to break the cycle and let normal `Arc` drops finish reclaiming
+	/// it. Returns how many values were swept this way.
+	pub fn collect(&self) -> usize {
+		let tables = self.tables.lock().unwrap().iter()
+			.filter_map(Weak::upgrade).collect::<Vec<_>>();
+		let functions = self.functions.lock().unwrap().iter()
+			.filter_map(Weak::upgrade).collect::<Vec<_>>();
+
+		tables.iter().for_each(|table| table.marked.store(false, Ordering::Relaxed));
+		functions.iter().for_each(|function| function.marked.store(false, Ordering::Relaxed));
+
+		let mut internal_refs = HashMap::<TrackedId, usize>::new();
+		let mut count = |id: TrackedId| *internal_refs.entry(id).or_insert(0) += 1;
+
+		for table in &tables {
+			if let Some(metatable) = &*table.metatable.lock().unwrap() {
+				count(Arc::as_ptr(metatable) as TrackedId);
+			}
+			table.data.lock().unwrap().iter().for_each(|(key, value)| {
+				Self::count_value(key, &mut count);
+				Self::count_value(value, &mut count);
+			});
+		}
+		// Sibling closures routinely share one up-value cell (see
+		// `MaybeUpValue::up_value`'s promotion), so counting "one reference per
+		// function that holds a clone of the cell" would overcount: the cell
+		// itself is what contributes to the held value's strong count, once,
+		// no matter how many functions share it. Dedup by the cell's own
+		// identity before counting what it points to.
+		let mut up_values = HashMap::<*const Mutex<Nillable>, (&Arc<Mutex<Nillable>>, usize)>::new();
+		for function in &functions {
+			function.up_values.iter().for_each(|up_value|
+				up_values.entry(Arc::as_ptr(up_value)).or_insert((up_value, 0)).1 += 1);
+		}
+		up_values.values()
+			.filter_map(|(up_value, _)| match &*up_value.lock().unwrap() {
+				NonNil(value) => Some(value.clone()),
+				Nil => None
+			})
+			.for_each(|value| Self::count_value(&value, &mut count));
+
+		// Every table/function above was upgraded from a `Weak`, so its
+		// `strong_count` includes the one held in `tables`/`functions` here;
+		// that one doesn't count as an external reference.
+		for table in &tables {
+			let internal = internal_refs.get(&(Arc::as_ptr(table) as TrackedId)).copied().unwrap_or(0);
+			if Arc::strong_count(table) - 1 > internal {
+				Self::mark_table(table);
+			}
+		}
+		for function in &functions {
+			let internal = internal_refs.get(&(Arc::as_ptr(function) as TrackedId)).copied().unwrap_or(0);
+			if Arc::strong_count(function) - 1 > internal {
+				Self::mark_function(function);
+			}
+		}
+		// A cell with more holders than the tracked functions sharing it (a VM
+		// register whose local was promoted into it, ...) is held from outside
+		// the arena, so the value it holds is live the same way.
+		up_values.values()
+			.filter(|&&(up_value, holders)| Arc::strong_count(up_value) > holders)
+			.filter_map(|&(up_value, _)| match &*up_value.lock().unwrap() {
+				NonNil(value) => Some(value.clone()),
+				Nil => None
+			})
+			.for_each(|value| Self::mark(&value));
+
+		let mut collected = 0;
+
+		self.tables.lock().unwrap().retain(|table| match table.upgrade() {
+			Some(table) if !table.marked.load(Ordering::Relaxed) => {
+				table.data.lock().unwrap().clear();
+				*table.metatable.lock().unwrap() = None;
+				collected += 1;
+				false
+			},
+			upgraded => upgraded.is_some()
+		});
+
+		self.functions.lock().unwrap().retain(|function| match function.upgrade() {
+			Some(function) if !function.marked.load(Ordering::Relaxed) => {
+				function.up_values.iter()
+					.for_each(|up_value| *up_value.lock().unwrap() = Nil);
+				collected += 1;
+				false
+			},
+			upgraded => upgraded.is_some()
+		});
+
+		collected
+	}
+
+	fn count_value(value: &Value, count: &mut impl FnMut(TrackedId)) {
+		match value {
+			Value::Table(table) => count(Arc::as_ptr(table) as TrackedId),
+			Value::Function(function) => count(Arc::as_ptr(function) as TrackedId),
+			Value::UserData {meta: Some(meta), ..} => count(Arc::as_ptr(meta) as TrackedId),
+			_ => {}
+		}
+	}
+
+	fn mark(value: &Value) {
+		match value {
+			Value::Table(table) => Self::mark_table(table),
+			Value::Function(function) => Self::mark_function(function),
+			Value::UserData {meta: Some(meta), ..} => Self::mark_table(meta),
+			_ => {}
+		}
+	}
+
+	fn mark_table(table: &Arc<Table>) {
+		// Already marked; stop here so a cycle can't recurse forever.
+		if table.marked.swap(true, Ordering::Relaxed) {return}
+
+		if let Some(metatable) = table.metatable.lock().unwrap().clone() {
+			Self::mark_table(&metatable);
+		}
+		table.data.lock().unwrap().iter()
+			.for_each(|(key, value)| {Self::mark(key); Self::mark(value)});
+	}
+
+	fn mark_function(function: &Arc<Function>) {
+		if function.marked.swap(true, Ordering::Relaxed) {return}
+
+		function.up_values.iter()
+			.filter_map(|up_value| match &*up_value.lock().unwrap() {
+				NonNil(value) => Some(value.clone()),
+				Nil => None
+			})
+			.for_each(|value| Self::mark(&value));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		ArithmeticPair, Arc, Any, IntoNillable, Nillable::NonNil, Table, UserData,
+		UserDataRegistry, Value, VirtualMachine
+	};
+
+	#[test]
+	fn arithmetic_coerce_promotes_to_float_only_when_either_operand_is_one() {
+		assert!(matches!(Value::Integer(1).arithmetic_coerce(&Value::Integer(2)),
+			Some(ArithmeticPair::Integer(1, 2))));
+		assert!(matches!(Value::Integer(1).arithmetic_coerce(&Value::Float(2.0)),
+			Some(ArithmeticPair::Float(a, b)) if a == 1.0 && b == 2.0));
+		assert!(matches!(Value::Float(1.0).arithmetic_coerce(&Value::Integer(2)),
+			Some(ArithmeticPair::Float(a, b)) if a == 1.0 && b == 2.0));
+		assert!(matches!(Value::Float(1.0).arithmetic_coerce(&Value::Float(2.0)),
+			Some(ArithmeticPair::Float(a, b)) if a == 1.0 && b == 2.0));
+	}
+
+	#[test]
+	fn arithmetic_coerce_rejects_non_numeric_operands() {
+		assert!(Value::Integer(1).arithmetic_coerce(&Value::new_string("2")).is_none());
+		assert!(Value::new_string("1").arithmetic_coerce(&Value::Integer(2)).is_none());
+	}
+
+	#[test]
+	fn user_data_registered_method_downcasts_back_to_the_concrete_type() {
+		struct Counter {
+			count: i64
+		}
+
+		impl UserData for Counter {
+			fn type_name(&self) -> &'static str {"counter"}
+			fn as_any(&self) -> &dyn Any {self}
+			fn as_any_mut(&mut self) -> &mut dyn Any {self}
+
+			fn register(registry: &mut UserDataRegistry) {
+				fn increment(arguments: Arc<Table>, _: &Arc<VirtualMachine>)
+						-> Result<Arc<Table>, String> {
+					let this = match arguments.index(&1i64.into()) {
+						NonNil(Value::UserData {data, ..}) => data,
+						_ => return Err("expected a Counter userdata as self".to_owned())
+					};
+
+					let mut this = this.lock().unwrap();
+					let counter = this.as_any_mut().downcast_mut::<Counter>()
+						.expect("registered method was called on the wrong userdata type");
+					counter.count += 1;
+
+					Ok(lua_tuple![counter.count].arc())
+				}
+
+				registry.method("increment", &increment);
+			}
+		}
+
+		let value = Value::new_user_data(Counter {count: 41});
+		let (data, meta) = match &value {
+			Value::UserData {data, meta} => (data.clone(), meta.clone().expect("increment should have registered a meta table")),
+			_ => panic!("new_user_data didn't produce a UserData value")
+		};
+
+		let increment = match meta.index(&Value::new_string("increment")) {
+			NonNil(Value::NativeFunction(function)) => function,
+			_ => panic!("register() didn't register 'increment'")
+		};
+
+		// `VirtualMachine` isn't part of this source tree (see the doc comment
+		// on `Arena`), so this assumes it implements
+		// `Default`, matching every other bare-state type in this module.
+		let vm = Arc::new(VirtualMachine::default());
+		let result = increment(lua_tuple![value.clone()].arc(), &vm).unwrap();
+
+		assert_eq!(result.index(&1i64.into()).option().as_ref().and_then(Value::integer), Some(42));
+		assert_eq!(data.lock().unwrap().as_any().downcast_ref::<Counter>().unwrap().count, 42);
 	}
 }